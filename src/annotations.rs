@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::compound::{Child, Compound};
+use crate::walk::{Step, Walk, Walker};
+
+use alloc::vec::Vec;
+
+use core::marker::PhantomData;
+
+use ranno::Annotation;
+
+/// Hash function used to build Merkle trees with [`HashAnnotation`].
+///
+/// The digest type is left to the implementation, so that any hash function
+/// can be used, such as Blake2b or Poseidon.
+pub trait MerkleHasher<L> {
+    /// The output of the hash function
+    type Digest: Copy + Eq;
+
+    /// Hashes a leaf
+    fn leaf(leaf: &L) -> Self::Digest;
+
+    /// Hashes a node given the digests of its children, in order, with `None`
+    /// for each empty slot
+    fn node<I>(children: I) -> Self::Digest
+    where
+        I: Iterator<Item = Option<Self::Digest>>;
+}
+
+/// Annotation turning any [`Compound`] with leaves of type `L` into a Merkle
+/// tree.
+///
+/// The digest of a node is computed by the [`MerkleHasher`] from the digests of
+/// its leaves and the annotations of its child nodes. The Merkle root of a tree
+/// is the digest of its root node, see [`HashAnnotation::compute`].
+pub struct HashAnnotation<H, L>
+where
+    H: MerkleHasher<L>,
+{
+    digest: H::Digest,
+    _marker: PhantomData<(H, L)>,
+}
+
+impl<H, L> HashAnnotation<H, L>
+where
+    H: MerkleHasher<L>,
+{
+    /// Returns the digest of the annotated node.
+    pub fn digest(&self) -> H::Digest {
+        self.digest
+    }
+
+    /// Computes the digest of the given node.
+    ///
+    /// Coherence rules prevent implementing [`Annotation`] for every compound
+    /// at once, so each compound implements it by calling this function in
+    /// [`Annotation::from_child`].
+    pub fn compute<C>(compound: &C) -> Self
+    where
+        C: Compound<Self, Leaf = L>,
+        Self: Annotation<C>,
+    {
+        let children = (0..).map_while(|i| match compound.child(i) {
+            Child::Leaf(leaf) => Some(Some(H::leaf(leaf))),
+            Child::Node(node) => Some(Some(node.anno().digest)),
+            Child::Empty => Some(None),
+            Child::EndOfNode => None,
+        });
+
+        HashAnnotation {
+            digest: H::node(children),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H, L> Clone for HashAnnotation<H, L>
+where
+    H: MerkleHasher<L>,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<H, L> Copy for HashAnnotation<H, L> where H: MerkleHasher<L> {}
+
+impl<H, L> PartialEq for HashAnnotation<H, L>
+where
+    H: MerkleHasher<L>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.digest == other.digest
+    }
+}
+
+impl<H, L> Eq for HashAnnotation<H, L> where H: MerkleHasher<L> {}
+
+impl<H, L> core::fmt::Debug for HashAnnotation<H, L>
+where
+    H: MerkleHasher<L>,
+    H::Digest: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("HashAnnotation").field(&self.digest).finish()
+    }
+}
+
+/// Inclusion proof of a leaf in a Merkle tree built using [`HashAnnotation`].
+///
+/// Constructed with [`Branch::proof`], and checked against the Merkle root
+/// using [`verify_proof`].
+///
+/// [`Branch::proof`]: crate::Branch::proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof<D> {
+    /// The levels of the branch, from the node holding the leaf up to the root
+    pub levels: Vec<ProofLevel<D>>,
+}
+
+/// A level of a [`MerkleProof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofLevel<D> {
+    /// The index of the child on the path to the leaf
+    pub index: usize,
+    /// The digests of all children of the node, with `None` for empty slots.
+    /// The entry for the child on the path is left out as `None`, since it is
+    /// computed from the level below.
+    pub children: Vec<Option<D>>,
+}
+
+/// Checks that the proof shows the leaf to be included in the Merkle tree with
+/// the given root digest.
+pub fn verify_proof<H, L>(
+    root: H::Digest,
+    proof: &MerkleProof<H::Digest>,
+    leaf: &L,
+) -> bool
+where
+    H: MerkleHasher<L>,
+{
+    let mut digest = H::leaf(leaf);
+
+    for level in &proof.levels {
+        if level.index >= level.children.len() {
+            return false;
+        }
+
+        let children = level.children.iter().enumerate().map(|(i, child)| {
+            match i == level.index {
+                true => Some(digest),
+                false => *child,
+            }
+        });
+        digest = H::node(children);
+    }
+
+    digest == root
+}
+
+/// Trait for leaves that are ordered by a key.
+pub trait Keyed<K> {
+    /// Returns a reference to the key of the leaf
+    fn key(&self) -> &K;
+}
+
+/// Annotation keeping track of the minimum key of the leaves in a subtree.
+///
+/// Subtrees without leaves are annotated with `PositiveInfinity`, which
+/// compares greater than any key. As with [`HashAnnotation`], compounds
+/// implement [`Annotation`] by calling [`MinKey::compute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MinKey<K> {
+    /// The minimum key of the subtree
+    Minimum(K),
+    /// The subtree has no leaves
+    PositiveInfinity,
+}
+
+impl<K> MinKey<K>
+where
+    K: Ord + Clone,
+{
+    /// Computes the minimum key under the given node.
+    pub fn compute<C>(compound: &C) -> Self
+    where
+        C: Compound<Self>,
+        C::Leaf: Keyed<K>,
+        Self: Annotation<C>,
+    {
+        let mut min = MinKey::PositiveInfinity;
+
+        for i in 0.. {
+            let key = match compound.child(i) {
+                Child::Leaf(leaf) => MinKey::Minimum(leaf.key().clone()),
+                Child::Node(node) => node.anno().clone(),
+                Child::Empty => continue,
+                Child::EndOfNode => break,
+            };
+            if key < min {
+                min = key;
+            }
+        }
+
+        min
+    }
+}
+
+/// Walker finding the leaf with the minimum key, using the [`MinKey`]
+/// annotation.
+pub struct FindMinKey;
+
+impl<C, K> Walker<C, MinKey<K>> for FindMinKey
+where
+    C: Compound<MinKey<K>>,
+    C::Leaf: Keyed<K>,
+    K: Ord + Clone,
+    MinKey<K>: Annotation<C>,
+{
+    fn walk(&mut self, walk: Walk<'_, C, MinKey<K>>) -> Step {
+        let mut min = MinKey::PositiveInfinity;
+        let mut step = Step::Advance;
+
+        for i in 0.. {
+            let (key, candidate) = match walk.child(i) {
+                Child::Leaf(leaf) => {
+                    (MinKey::Minimum(leaf.key().clone()), Step::Found(i))
+                }
+                Child::Node(node) => (node.anno().clone(), Step::Into(i)),
+                Child::Empty => continue,
+                Child::EndOfNode => break,
+            };
+            if key < min {
+                min = key;
+                step = candidate;
+            }
+        }
+
+        step
+    }
+}
@@ -4,12 +4,18 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+use crate::annotations::{
+    HashAnnotation, MerkleHasher, MerkleProof, ProofLevel,
+};
 use crate::compound::{Child, Compound};
-use crate::walk::{AllLeaves, Step, Walk, Walker};
+use crate::walk::{
+    AllLeaves, Budgeted, Direction, FollowPath, Step, Walk, WalkError, Walker,
+};
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
+use core::cell::Ref;
 use core::ops::Deref;
 
 use ranno::{Annotated, Annotation};
@@ -59,8 +65,25 @@ impl<'a, C, A> Level<'a, C, A> {
     }
 }
 
+impl<'a, C, A> Level<'a, C, A>
+where
+    A: Annotation<C>,
+{
+    /// Returns the annotation of the node at this level, or `None` for the
+    /// root, which is not annotated.
+    pub fn annotation(&self) -> Option<Ref<'a, A>> {
+        match self.node {
+            LevelNode::Root(_) => None,
+            LevelNode::Val(annotated) => Some(annotated.anno()),
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct PartialBranch<'a, C, A>(Vec<Level<'a, C, A>>);
+pub struct PartialBranch<'a, C, A> {
+    levels: Vec<Level<'a, C, A>>,
+    direction: Direction,
+}
 
 impl<'a, C, A> Deref for LevelNode<'a, C, A> {
     type Target = C;
@@ -74,44 +97,73 @@ impl<'a, C, A> Deref for LevelNode<'a, C, A> {
 }
 
 impl<'a, C, A> PartialBranch<'a, C, A> {
-    fn new(root: &'a C) -> Self {
-        PartialBranch(vec![Level::new_root(root)])
-    }
-
     pub fn depth(&self) -> usize {
-        self.0.len()
+        self.levels.len()
     }
 
-    pub fn levels(&self) -> &[Level<C, A>] {
-        &self.0
+    pub fn levels(&self) -> &[Level<'_, C, A>] {
+        &self.levels
     }
 
-    fn top(&self) -> &Level<C, A> {
-        self.0.last().expect("Never empty")
+    fn top(&self) -> &Level<'_, C, A> {
+        self.levels.last().expect("Never empty")
     }
 
     fn top_mut(&mut self) -> &mut Level<'a, C, A> {
-        self.0.last_mut().expect("Never empty")
+        self.levels.last_mut().expect("Never empty")
     }
 
-    fn advance(&mut self) {
-        *self.top_mut().index_mut() += 1;
+    // Moves to the next child in the walk direction. Walking backwards, levels
+    // left without children to visit are popped.
+    fn step(&mut self) -> Option<()> {
+        match self.direction {
+            Direction::Forward => {
+                *self.top_mut().index_mut() += 1;
+                Some(())
+            }
+            Direction::Backward => loop {
+                let index = self.top_mut().index_mut();
+                if *index > 0 {
+                    *index -= 1;
+                    return Some(());
+                }
+                self.pop()?;
+            },
+        }
     }
 
     fn pop(&mut self) -> Option<Level<'a, C, A>> {
         // We never pop the root
-        if self.0.len() > 1 {
-            self.0.pop()
+        if self.levels.len() > 1 {
+            self.levels.pop()
         } else {
             None
         }
     }
+
+    pub fn path(&self) -> impl Iterator<Item = usize> + '_ {
+        self.levels.iter().map(|level| level.index())
+    }
+
+    fn reverse(&mut self) {
+        self.direction = self.direction.reversed();
+    }
 }
 
 impl<'a, C, A> PartialBranch<'a, C, A>
 where
     C: Compound<A>,
 {
+    fn new(root: &'a C, direction: Direction) -> Self {
+        let mut level = Level::new_root(root);
+        *level.index_mut() = direction.start::<C, A>(root);
+
+        PartialBranch {
+            levels: vec![level],
+            direction,
+        }
+    }
+
     fn leaf(&self) -> Option<&C::Leaf> {
         let top = self.top();
         let index = top.index();
@@ -123,6 +175,18 @@ where
     }
 
     fn walk<W>(&mut self, walker: &mut W) -> Option<()>
+    where
+        W: Walker<C, A>,
+    {
+        self.try_walk(walker, None, usize::MAX).unwrap_or(None)
+    }
+
+    fn try_walk<W>(
+        &mut self,
+        walker: &mut W,
+        mut budget: Option<&mut u64>,
+        max_depth: usize,
+    ) -> Result<Option<()>, WalkError>
     where
         W: Walker<C, A>,
     {
@@ -136,29 +200,47 @@ where
         loop {
             match core::mem::replace(&mut state, State::Init) {
                 State::Init => (),
-                State::Push(push) => self.0.push(push),
-                State::Pop => match self.pop() {
-                    Some(_) => {
-                        self.advance();
+                State::Push(push) => {
+                    if self.levels.len() >= max_depth {
+                        return Err(WalkError::DepthLimitExceeded {
+                            path: self.path().collect(),
+                        });
+                    }
+                    self.levels.push(push)
+                }
+                State::Pop => {
+                    // the root is never popped, so this ends the walk
+                    if self.pop().is_none() || self.step().is_none() {
+                        return Ok(None);
                     }
-                    None => return None,
-                },
+                }
             }
 
+            if let Some(steps) = budget.as_deref_mut() {
+                if *steps == 0 {
+                    return Err(WalkError::BudgetExceeded);
+                }
+                *steps -= 1;
+            }
+
+            let direction = self.direction;
             let top = self.top_mut();
-            let step = walker.walk(Walk::new(&**top, top.index()));
+            let step = walker.walk(Walk::new(&**top, top.index(), direction));
 
             match step {
                 Step::Found(walk_index) => {
-                    *top.index_mut() += walk_index;
-                    return Some(());
+                    *top.index_mut() =
+                        direction.offset(top.index(), walk_index);
+                    return Ok(Some(()));
                 }
                 Step::Into(walk_index) => {
-                    *top.index_mut() += walk_index;
+                    *top.index_mut() =
+                        direction.offset(top.index(), walk_index);
                     let index = top.index();
                     let top_child = top.child(index);
                     if let Child::Node(n) = top_child {
-                        let level: Level<'_, C, A> = Level::new_val(n);
+                        let mut level: Level<'_, C, A> = Level::new_val(n);
+                        *level.index_mut() = direction.start::<C, A>(&*level);
 
                         // Extend the lifetime of the Level.
                         //
@@ -206,7 +288,7 @@ where
                 }
                 Step::Advance => state = State::Pop,
                 Step::Abort => {
-                    return None;
+                    return Ok(None);
                 }
             }
         }
@@ -220,15 +302,45 @@ impl<'a, C, A> Branch<'a, C, A> {
     }
 
     /// Returns a slice into the levels of the tree.
-    pub fn levels(&self) -> &[Level<C, A>] {
+    pub fn levels(&self) -> &[Level<'_, C, A>] {
         self.0.levels()
     }
+
+    /// Returns the child indices taken from the root to reach the leaf.
+    pub fn path(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.path()
+    }
+
+    /// Reverses the direction of the branch, so that iterating it goes
+    /// through the leaves preceding the current one, instead of the ones
+    /// following it, or vice versa for branches from a reverse walk.
+    pub fn rev(mut self) -> Self {
+        self.0.reverse();
+        self
+    }
+
+    /// Returns the annotations of the nodes along the branch, from the first
+    /// level below the root down to the node holding the leaf.
+    pub fn annotations(&self) -> impl Iterator<Item = Ref<'a, A>> + '_
+    where
+        A: Annotation<C>,
+    {
+        self.0.levels.iter().filter_map(Level::annotation)
+    }
 }
 
 impl<'a, C, A> Branch<'a, C, A>
 where
     C: Compound<A>,
 {
+    /// Returns a reference to the leaf the branch points at.
+    ///
+    /// Equivalent to de-referencing the branch, for use where a method reads
+    /// clearer than the deref operator.
+    pub fn leaf(&self) -> &C::Leaf {
+        self.0.leaf().expect("Invalid branch")
+    }
+
     /// Returns a branch that maps the leaf to a specific value.
     /// Used in maps for example, to get easy access to the value of the KV-pair
     pub fn map_leaf<M>(
@@ -241,15 +353,109 @@ where
         }
     }
 
+    /// Walks another tree using a walker constructed from the leaf of this
+    /// branch, returning both branches if the second walk succeeds.
+    /// Used for secondary index lookups for example, where the leaf of an index
+    /// tree determines where to find the data in another.
+    pub fn join<'b, D, B, W, F>(
+        self,
+        root: &'b D,
+        closure: F,
+    ) -> Option<(Self, Branch<'b, D, B>)>
+    where
+        D: Compound<B>,
+        W: Walker<D, B>,
+        F: FnOnce(&C::Leaf) -> W,
+    {
+        let walker = closure(self.0.leaf().expect("Invalid branch"));
+        Branch::walk(root, walker).map(|joined| (self, joined))
+    }
+
     /// Performs a tree walk, returning either a valid branch or None if the
     /// walk failed.
     pub fn walk<W>(root: &'a C, mut walker: W) -> Option<Self>
     where
         W: Walker<C, A>,
     {
-        let mut partial = PartialBranch::new(root);
+        let mut partial = PartialBranch::new(root, Direction::Forward);
         partial.walk(&mut walker).map(|()| Branch(partial))
     }
+
+    /// Constructs a branch by following the given child indices from the root,
+    /// returning None if they do not lead to a leaf.
+    ///
+    /// Used to replay the [`path`] of a previously constructed branch.
+    ///
+    /// [`path`]: Branch::path
+    pub fn from_path(root: &'a C, path: &[usize]) -> Option<Self> {
+        Branch::walk(root, FollowPath::new(path))
+    }
+
+    /// Performs a tree walk in reverse, returning either a valid branch or None
+    /// if the walk failed.
+    ///
+    /// The walker is presented the children of each node from last to first,
+    /// and iterating the resulting branch continues backwards.
+    pub fn walk_rev<W>(root: &'a C, mut walker: W) -> Option<Self>
+    where
+        W: Walker<C, A>,
+    {
+        let mut partial = PartialBranch::new(root, Direction::Backward);
+        partial.walk(&mut walker).map(|()| Branch(partial))
+    }
+
+    /// Performs a tree walk bounded by the budget and maximum depth of the given
+    /// walker, returning an error if either is exceeded before the walk
+    /// completes.
+    pub fn walk_budgeted<W>(
+        root: &'a C,
+        mut walker: Budgeted<W>,
+    ) -> Result<Option<Self>, WalkError>
+    where
+        W: Walker<C, A>,
+    {
+        let mut partial = PartialBranch::new(root, Direction::Forward);
+        let (walker, steps, max_depth) = walker.parts_mut();
+        let walked = partial.try_walk(walker, steps, max_depth)?;
+        Ok(walked.map(|()| Branch(partial)))
+    }
+}
+
+impl<'a, C, H, L> Branch<'a, C, HashAnnotation<H, L>>
+where
+    C: Compound<HashAnnotation<H, L>, Leaf = L>,
+    H: MerkleHasher<L>,
+    HashAnnotation<H, L>: Annotation<C>,
+{
+    /// Returns the inclusion proof of the leaf in the Merkle tree, made of the
+    /// digests of the children of every node along the branch.
+    ///
+    /// See [`verify_proof`].
+    ///
+    /// [`verify_proof`]: crate::verify_proof
+    pub fn proof(&self) -> MerkleProof<H::Digest> {
+        let levels = self
+            .0
+            .levels
+            .iter()
+            .rev()
+            .map(|level| {
+                let index = level.index();
+                let children = (0..)
+                    .map_while(|i| match level.child(i) {
+                        _ if i == index => Some(None),
+                        Child::Leaf(leaf) => Some(Some(H::leaf(leaf))),
+                        Child::Node(node) => Some(Some(node.anno().digest())),
+                        Child::Empty => Some(None),
+                        Child::EndOfNode => None,
+                    })
+                    .collect();
+                ProofLevel { index, children }
+            })
+            .collect();
+
+        MerkleProof { levels }
+    }
 }
 
 /// Represents an immutable branch view into a collection.
@@ -266,7 +472,7 @@ where
     type Target = C::Leaf;
 
     fn deref(&self) -> &Self::Target {
-        self.0.leaf().expect("Invalid branch")
+        self.leaf()
     }
 }
 
@@ -286,7 +492,17 @@ where
     type Target = M;
 
     fn deref(&self) -> &M {
-        (self.closure)(&self.inner)
+        self.leaf()
+    }
+}
+
+impl<'a, C, A, M> MappedBranch<'a, C, A, M>
+where
+    C: Compound<A>,
+{
+    /// Returns a reference to the mapped leaf the branch points at.
+    pub fn leaf(&self) -> &M {
+        (self.closure)(self.inner.leaf())
     }
 }
 
@@ -323,9 +539,9 @@ where
                 *self = BranchIterator::Intermediate(branch, walker);
             }
             BranchIterator::Intermediate(mut branch, mut walker) => {
-                branch.0.advance();
                 // access partial branch
-                match branch.0.walk(&mut walker) {
+                match branch.0.step().and_then(|()| branch.0.walk(&mut walker))
+                {
                     None => {
                         *self = BranchIterator::Exhausted;
                         return None;
@@ -392,9 +608,9 @@ where
                 *self = Self::Intermediate(branch, walker);
             }
             Self::Intermediate(mut branch, mut walker) => {
-                branch.inner.0.advance();
+                let partial = &mut branch.inner.0;
                 // access partial branch
-                match branch.inner.0.walk(&mut walker) {
+                match partial.step().and_then(|()| partial.walk(&mut walker)) {
                     None => {
                         *self = Self::Exhausted;
                         return None;
@@ -4,8 +4,10 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::compound::{Child, ChildMut, Compound};
-use crate::walk::{AllLeaves, Step, Walk, Walker};
+use crate::compound::{Child, ChildMut, Compound, OwnedChild};
+use crate::walk::{
+    AllLeaves, Budgeted, Direction, FollowPath, Step, Walk, WalkError, Walker,
+};
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
@@ -99,37 +101,59 @@ impl<'a, C, A> LevelMut<'a, C, A> {
 }
 
 #[derive(Debug)]
-pub struct PartialBranchMut<'a, C, A>(Vec<LevelMut<'a, C, A>>);
+pub struct PartialBranchMut<'a, C, A> {
+    levels: Vec<LevelMut<'a, C, A>>,
+    direction: Direction,
+}
 
 impl<'a, C, A> PartialBranchMut<'a, C, A> {
-    fn new(root: &'a mut C) -> Self {
-        PartialBranchMut(vec![LevelMut::new_root(root)])
-    }
-
     pub fn depth(&self) -> usize {
-        self.0.len()
+        self.levels.len()
     }
 
-    fn top(&self) -> &LevelMut<C, A> {
-        self.0.last().expect("Never empty")
+    fn top(&self) -> &LevelMut<'_, C, A> {
+        self.levels.last().expect("Never empty")
     }
 
     fn top_mut(&mut self) -> &mut LevelMut<'a, C, A> {
-        self.0.last_mut().expect("Never empty")
+        self.levels.last_mut().expect("Never empty")
     }
 
-    fn advance(&mut self) {
-        *self.top_mut().index_mut() += 1
+    // Moves to the next child in the walk direction. Walking backwards, levels
+    // left without children to visit are popped.
+    fn step(&mut self) -> Option<()> {
+        match self.direction {
+            Direction::Forward => {
+                *self.top_mut().index_mut() += 1;
+                Some(())
+            }
+            Direction::Backward => loop {
+                let index = self.top_mut().index_mut();
+                if *index > 0 {
+                    *index -= 1;
+                    return Some(());
+                }
+                self.pop()?;
+            },
+        }
     }
 
     fn pop(&mut self) -> Option<LevelMut<'a, C, A>> {
         // We never pop the root
-        if self.0.len() > 1 {
-            self.0.pop()
+        if self.levels.len() > 1 {
+            self.levels.pop()
         } else {
             None
         }
     }
+
+    pub fn path(&self) -> impl Iterator<Item = usize> + '_ {
+        self.levels.iter().map(|level| level.index())
+    }
+
+    fn reverse(&mut self) {
+        self.direction = self.direction.reversed();
+    }
 }
 
 impl<'a, C, A> PartialBranchMut<'a, C, A>
@@ -137,7 +161,42 @@ where
     C: Compound<A>,
     A: Annotation<C>,
 {
+    fn new(root: &'a mut C, direction: Direction) -> Self {
+        let index = direction.start::<C, A>(root);
+        let mut level = LevelMut::new_root(root);
+        *level.index_mut() = index;
+
+        PartialBranchMut {
+            levels: vec![level],
+            direction,
+        }
+    }
+
+    // Pops the levels bottom-up, computing the annotation of each node once
+    // the levels below it are gone, so that no node is read while a level
+    // still borrows it mutably. The root is not annotated.
+    fn pop_annotations(&mut self) -> Vec<A> {
+        let mut annotations = vec![];
+        while let Some(level) = self.pop() {
+            annotations.push(A::from_child(&*level));
+        }
+        annotations.reverse();
+        annotations
+    }
+
     fn walk<W>(&mut self, walker: &mut W) -> Option<()>
+    where
+        W: Walker<C, A>,
+    {
+        self.try_walk(walker, None, usize::MAX).unwrap_or(None)
+    }
+
+    fn try_walk<W>(
+        &mut self,
+        walker: &mut W,
+        mut budget: Option<&mut u64>,
+        max_depth: usize,
+    ) -> Result<Option<()>, WalkError>
     where
         W: Walker<C, A>,
     {
@@ -151,30 +210,48 @@ where
         loop {
             match mem::replace(&mut state, State::Init) {
                 State::Init => (),
-                State::Push(push) => self.0.push(push),
-                State::Pop => match self.pop() {
-                    Some(_) => {
-                        self.advance();
+                State::Push(push) => {
+                    if self.levels.len() >= max_depth {
+                        return Err(WalkError::DepthLimitExceeded {
+                            path: self.path().collect(),
+                        });
+                    }
+                    self.levels.push(push)
+                }
+                State::Pop => {
+                    // the root is never popped, so this ends the walk
+                    if self.pop().is_none() || self.step().is_none() {
+                        return Ok(None);
                     }
-                    None => return None,
-                },
+                }
+            }
+
+            if let Some(steps) = budget.as_deref_mut() {
+                if *steps == 0 {
+                    return Err(WalkError::BudgetExceeded);
+                }
+                *steps -= 1;
             }
 
+            let direction = self.direction;
             let top = self.top_mut();
-            let step = walker.walk(Walk::new(&**top, top.index()));
+            let step = walker.walk(Walk::new(&**top, top.index(), direction));
 
             match step {
                 Step::Found(walk_index) => {
-                    *top.index_mut() += walk_index;
-                    return Some(());
+                    *top.index_mut() =
+                        direction.offset(top.index(), walk_index);
+                    return Ok(Some(()));
                 }
                 Step::Into(walk_index) => {
-                    *top.index_mut() += walk_index;
+                    *top.index_mut() =
+                        direction.offset(top.index(), walk_index);
                     let index = top.index();
                     let top_child = top.child_mut(index);
                     if let ChildMut::Node(n) = top_child {
-                        let level: LevelMut<'_, C, A> =
+                        let mut level: LevelMut<'_, C, A> =
                             LevelMut::new_val(n.child_mut());
+                        *level.index_mut() = direction.start::<C, A>(&*level);
 
                         // Extend the lifetime of the Level.
                         // See comment in `Branch::walk` for justification.
@@ -186,7 +263,7 @@ where
                     }
                 }
                 Step::Advance => state = State::Pop,
-                Step::Abort => return None,
+                Step::Abort => return Ok(None),
             }
         }
     }
@@ -223,6 +300,19 @@ impl<'a, C, A> BranchMut<'a, C, A> {
     pub fn depth(&self) -> usize {
         self.0.depth()
     }
+
+    /// Returns the child indices taken from the root to reach the leaf.
+    pub fn path(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.path()
+    }
+
+    /// Reverses the direction of the branch, so that iterating it goes
+    /// through the leaves preceding the current one, instead of the ones
+    /// following it, or vice versa for branches from a reverse walk.
+    pub fn rev(mut self) -> Self {
+        self.0.reverse();
+        self
+    }
 }
 
 impl<'a, C, A> BranchMut<'a, C, A>
@@ -230,15 +320,107 @@ where
     C: Compound<A>,
     A: Annotation<C>,
 {
+    /// Consumes the branch, returning the annotations of the nodes along it,
+    /// from the first level below the root down to the node holding the leaf.
+    ///
+    /// The annotations are computed from the bottom up as the branch is
+    /// released, and so reflect any change made to the leaf through it.
+    pub fn into_annotations(mut self) -> Vec<A> {
+        self.0.pop_annotations()
+    }
+
     /// Performs a tree walk, returning either a valid branch or None if the
     /// walk failed.
     pub fn walk<W>(root: &'a mut C, mut walker: W) -> Option<Self>
     where
         W: Walker<C, A>,
     {
-        let mut partial = PartialBranchMut::new(root);
+        let mut partial = PartialBranchMut::new(root, Direction::Forward);
         partial.walk(&mut walker).map(|()| BranchMut(partial))
     }
+
+    /// Constructs a branch by following the given child indices from the root,
+    /// returning None if they do not lead to a leaf.
+    ///
+    /// Used to replay the [`path`] of a previously constructed branch.
+    ///
+    /// [`path`]: BranchMut::path
+    pub fn from_path(root: &'a mut C, path: &[usize]) -> Option<Self> {
+        BranchMut::walk(root, FollowPath::new(path))
+    }
+
+    /// Performs a tree walk in reverse, returning either a valid branch or None
+    /// if the walk failed.
+    ///
+    /// The walker is presented the children of each node from last to first,
+    /// and iterating the resulting branch continues backwards.
+    pub fn walk_rev<W>(root: &'a mut C, mut walker: W) -> Option<Self>
+    where
+        W: Walker<C, A>,
+    {
+        let mut partial = PartialBranchMut::new(root, Direction::Backward);
+        partial.walk(&mut walker).map(|()| BranchMut(partial))
+    }
+
+    /// Performs a tree walk bounded by the budget and maximum depth of the given
+    /// walker, returning an error if either is exceeded before the walk
+    /// completes.
+    pub fn walk_budgeted<W>(
+        root: &'a mut C,
+        mut walker: Budgeted<W>,
+    ) -> Result<Option<Self>, WalkError>
+    where
+        W: Walker<C, A>,
+    {
+        let mut partial = PartialBranchMut::new(root, Direction::Forward);
+        let (walker, steps, max_depth) = walker.parts_mut();
+        let walked = partial.try_walk(walker, steps, max_depth)?;
+        Ok(walked.map(|()| BranchMut(partial)))
+    }
+
+    /// Returns a branch that reports the changes made to its leaf to the given
+    /// observer.
+    pub fn observe<O>(self, observer: O) -> ObservedBranchMut<'a, C, A, O>
+    where
+        C::Leaf: Clone + PartialEq,
+        O: Observer<C::Leaf, A>,
+    {
+        ObservedBranchMut {
+            inner: self,
+            observer,
+            old: None,
+        }
+    }
+
+    /// Takes the leaf out of the tree, leaving an empty slot in its place.
+    ///
+    /// The `fix` closure is then called on every node of the branch, from the
+    /// bottom up to the root, letting the data structure restore its
+    /// invariants. Returns `None`, leaving the tree untouched, if the compound
+    /// does not support taking its children out. See [`Compound::take_child`].
+    pub fn take_leaf<F>(mut self, mut fix: F) -> Option<C::Leaf>
+    where
+        F: FnMut(&mut C),
+    {
+        let top = self.0.top_mut();
+        let index = top.index();
+
+        let leaf = match top.take_child(index)? {
+            OwnedChild::Leaf(leaf) => leaf,
+            OwnedChild::Node(_) => panic!("Invalid branch"),
+        };
+
+        // levels are popped after being fixed, so no level referring into a
+        // node is left when its parent is changed
+        loop {
+            fix(self.0.top_mut());
+            if self.0.pop().is_none() {
+                break;
+            }
+        }
+
+        Some(leaf)
+    }
 }
 
 impl<'a, C, A> BranchMut<'a, C, A>
@@ -327,6 +509,101 @@ where
     }
 }
 
+/// Receives the changes made to leaves through an [`ObservedBranchMut`].
+pub trait Observer<L, A> {
+    /// Called with the path to the changed leaf - the child indices taken from
+    /// the root - together with the leaf before and after the change.
+    fn leaf_updated(&mut self, path: &[usize], old: &L, new: &L);
+
+    /// Called after [`leaf_updated`] with the same path, together with the
+    /// annotations of the nodes along it after the change, from the first level
+    /// below the root down to the node holding the leaf.
+    ///
+    /// The annotations from before the change are not provided, since walking
+    /// to a leaf mutably already invalidates them. Does nothing by default.
+    ///
+    /// [`leaf_updated`]: Observer::leaf_updated
+    fn annotations_updated(&mut self, _path: &[usize], _annotations: &[A]) {}
+}
+
+impl<L, A, F> Observer<L, A> for F
+where
+    F: FnMut(&[usize], &L, &L),
+{
+    fn leaf_updated(&mut self, path: &[usize], old: &L, new: &L) {
+        self(path, old, new)
+    }
+}
+
+/// A `BranchMut` reporting the changes made to its leaf to an [`Observer`].
+///
+/// The leaf is cloned the first time it is mutably de-referenced, and the
+/// observer is notified when the branch is dropped, unless the leaf compares
+/// equal to its clone. The annotations along the branch are then recomputed
+/// from the bottom up as the branch is released, and reported as well.
+/// Branches that are never observed carry no extra cost.
+///
+/// Only updates made through the branch are observed. Leaves inserted or
+/// removed by other means, such as [`BranchMut::take_leaf`], are not reported.
+pub struct ObservedBranchMut<'a, C, A, O>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    C::Leaf: Clone + PartialEq,
+    O: Observer<C::Leaf, A>,
+{
+    inner: BranchMut<'a, C, A>,
+    observer: O,
+    old: Option<C::Leaf>,
+}
+
+impl<'a, C, A, O> Deref for ObservedBranchMut<'a, C, A, O>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    C::Leaf: Clone + PartialEq,
+    O: Observer<C::Leaf, A>,
+{
+    type Target = C::Leaf;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<'a, C, A, O> DerefMut for ObservedBranchMut<'a, C, A, O>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    C::Leaf: Clone + PartialEq,
+    O: Observer<C::Leaf, A>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.old.is_none() {
+            self.old = Some(self.inner.clone());
+        }
+        &mut self.inner
+    }
+}
+
+impl<'a, C, A, O> Drop for ObservedBranchMut<'a, C, A, O>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    C::Leaf: Clone + PartialEq,
+    O: Observer<C::Leaf, A>,
+{
+    fn drop(&mut self) {
+        if let Some(old) = self.old.take().filter(|old| *old != *self.inner) {
+            let path: Vec<_> = self.inner.0.path().collect();
+            self.observer.leaf_updated(&path, &old, &self.inner);
+
+            let annotations = self.inner.0.pop_annotations();
+            self.observer.annotations_updated(&path, &annotations);
+        }
+    }
+}
+
 // iterators
 
 pub enum BranchMutIterator<'a, C, A, W> {
@@ -363,9 +640,9 @@ where
                 *self = BranchMutIterator::Intermediate(branch, walker);
             }
             BranchMutIterator::Intermediate(mut branch, mut walker) => {
-                branch.0.advance();
                 // access partial branch
-                match branch.0.walk(&mut walker) {
+                match branch.0.step().and_then(|()| branch.0.walk(&mut walker))
+                {
                     None => {
                         *self = BranchMutIterator::Exhausted;
                         return None;
@@ -431,9 +708,9 @@ where
                 *self = Self::Intermediate(branch, walker);
             }
             Self::Intermediate(mut branch, mut walker) => {
-                branch.inner.0.advance();
+                let partial = &mut branch.inner.0;
                 // access partial branch
-                match branch.inner.0.walk(&mut walker) {
+                match partial.step().and_then(|()| partial.walk(&mut walker)) {
                     None => {
                         *self = Self::Exhausted;
                         return None;
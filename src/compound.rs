@@ -6,7 +6,10 @@
 
 use alloc::boxed::Box;
 
-use ranno::Annotated;
+use core::cell::Ref;
+use core::mem;
+
+use ranno::{Annotated, Annotation};
 
 /// The response of the [`child`] method on a [`Compound`] node.
 ///
@@ -44,16 +47,159 @@ where
     EndOfNode,
 }
 
+impl<'a, C, A> ChildMut<'a, C, A>
+where
+    C: Compound<A>,
+{
+    /// Replaces the child with another of the same kind, returning the
+    /// replaced child.
+    ///
+    /// Leaves can only be replaced by leaves and nodes by nodes. Otherwise, the
+    /// given child is returned as an error.
+    pub fn replace(
+        self,
+        child: OwnedChild<C, A>,
+    ) -> Result<OwnedChild<C, A>, OwnedChild<C, A>> {
+        match (self, child) {
+            (ChildMut::Leaf(leaf), OwnedChild::Leaf(new)) => {
+                Ok(OwnedChild::Leaf(mem::replace(leaf, new)))
+            }
+            (ChildMut::Node(node), OwnedChild::Node(new)) => {
+                Ok(OwnedChild::Node(mem::replace(node, new)))
+            }
+            (_, child) => Err(child),
+        }
+    }
+}
+
+/// A child owned by the caller, as taken out of or put into a [`Compound`]
+/// node.
+#[derive(Debug)]
+pub enum OwnedChild<C, A>
+where
+    C: Compound<A>,
+{
+    /// Child is a leaf
+    Leaf(C::Leaf),
+    /// Child is an annotated subtree node
+    Node(Annotated<Box<C>, A>),
+}
+
+/// The action to take after a closure passed to [`fold_annotations`] has
+/// looked at a child.
+///
+/// [`fold_annotations`]: Compound::fold_annotations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fold {
+    /// Continue with the next child, without descending
+    Continue,
+    /// Descend into the child, if it is a subtree node
+    Descend,
+    /// Stop folding
+    Stop,
+}
+
 /// A type that can recursively contain itself and leaves.
 pub trait Compound<A>: Sized {
     /// The leaf type of the compound collection
     type Leaf;
 
     /// Returns a reference to a possible child at specified index
-    fn child(&self, index: usize) -> Child<Self, A>;
+    fn child(&self, index: usize) -> Child<'_, Self, A>;
 
     /// Returns a mutable reference to a possible child at specified index
-    fn child_mut(&mut self, index: usize) -> ChildMut<Self, A>;
+    fn child_mut(&mut self, index: usize) -> ChildMut<'_, Self, A>;
+
+    /// Takes the child at the specified index out of the node, leaving an empty
+    /// slot in its place.
+    ///
+    /// Compounds that cannot represent empty slots keep the default, which
+    /// returns `None` without changing the node.
+    fn take_child(&mut self, _index: usize) -> Option<OwnedChild<Self, A>> {
+        None
+    }
+
+    /// Replaces the child at the specified index with another of the same
+    /// kind, returning the replaced child.
+    ///
+    /// If the slot is empty or holds a different kind of child, the given child
+    /// is returned as an error.
+    fn replace_child(
+        &mut self,
+        index: usize,
+        child: OwnedChild<Self, A>,
+    ) -> Result<OwnedChild<Self, A>, OwnedChild<Self, A>> {
+        self.child_mut(index).replace(child)
+    }
+
+    /// Folds over the tree, letting the `node` closure account for whole
+    /// subtrees using their annotation, and only descending into them when it
+    /// asks to.
+    ///
+    /// Subtrees wholly inside a query range can then be consumed by their
+    /// annotation, leaving only the ones at the range boundaries to be
+    /// descended into. The `leaf` closure is called for each leaf reached.
+    fn fold_annotations<T, N, L>(&self, init: T, mut node: N, mut leaf: L) -> T
+    where
+        A: Annotation<Self>,
+        N: FnMut(&mut T, &A) -> Fold,
+        L: FnMut(&mut T, &Self::Leaf) -> Fold,
+    {
+        let mut acc = init;
+        let mut stack = vec![(self, 0)];
+
+        while let Some(top) = stack.last_mut() {
+            let (compound, index) = *top;
+            top.1 += 1;
+
+            let fold = match compound.child(index) {
+                Child::Leaf(l) => leaf(&mut acc, l),
+                Child::Node(annotated) => {
+                    let fold = node(&mut acc, &annotated.anno());
+                    if fold == Fold::Descend {
+                        stack.push((annotated.child(), 0));
+                    }
+                    fold
+                }
+                Child::Empty => Fold::Continue,
+                Child::EndOfNode => {
+                    stack.pop();
+                    Fold::Continue
+                }
+            };
+
+            if fold == Fold::Stop {
+                break;
+            }
+        }
+
+        acc
+    }
+
+    /// Returns the annotation of the subtree found by following the given
+    /// child indices from this node.
+    ///
+    /// Only the nodes along the path are visited, and no leaf is ever read.
+    /// Returns `None` if the path is empty or does not lead to a subtree node.
+    fn annotation_at(&self, path: &[usize]) -> Option<Ref<'_, A>>
+    where
+        A: Annotation<Self>,
+    {
+        let (last, path) = path.split_last()?;
+
+        let mut node = self;
+        for index in path {
+            match node.child(*index) {
+                Child::Node(annotated) => node = annotated.child(),
+                _ => return None,
+            }
+        }
+
+        match node.child(*last) {
+            Child::Node(annotated) => Some(annotated.anno()),
+            _ => None,
+        }
+    }
 }
 
 /// Marker trait to signal that a data structure can allow mutable access to
@@ -66,3 +212,34 @@ pub trait Compound<A>: Sized {
 /// Note that this is still safe to implement, since it can only cause logical
 /// errors, not undefined behaviour,
 pub trait MutableLeaves {}
+
+/// Trait that provides a [`transaction`] method to any [`Compound`] that can be
+/// cloned.
+///
+/// [`transaction`]: Transaction::transaction
+pub trait Transaction<A>: Sized + Compound<A> + Clone {
+    /// Applies the closure to a copy of the tree, replacing the tree with the
+    /// copy only if the closure succeeds.
+    ///
+    /// If the closure returns an error or panics, the tree is left untouched.
+    /// Since subtrees are owned and not shared, the copy is a clone of the
+    /// whole tree.
+    fn transaction<T, E, F>(&mut self, closure: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>;
+}
+
+impl<C, A> Transaction<A> for C
+where
+    C: Compound<A> + Clone,
+{
+    fn transaction<T, E, F>(&mut self, closure: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        let mut tx = self.clone();
+        let value = closure(&mut tx)?;
+        *self = tx;
+        Ok(value)
+    }
+}
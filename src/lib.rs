@@ -9,7 +9,7 @@
 //!
 //! - [`Compound`], a trait for a generic way to implement tree structures.
 //! - [`Branch`] and [`BranchMut`], types for representing branches in tree-formed
-//! data as well as methods for searching.
+//!   data as well as methods for searching.
 //! - [`Walker`], a trait for a generic way of walking [`Compound`]s.
 
 #![no_std]
@@ -19,13 +19,25 @@
 #[macro_use]
 extern crate alloc;
 
+mod annotations;
 mod branch;
 mod branch_mut;
 mod compound;
+mod stats;
 mod walk;
 
+pub use annotations::{
+    verify_proof, FindMinKey, HashAnnotation, Keyed, MerkleHasher, MerkleProof,
+    MinKey, ProofLevel,
+};
 pub use branch::Branch;
-pub use branch_mut::BranchMut;
+pub use branch_mut::{BranchMut, ObservedBranchMut, Observer};
 
-pub use compound::{Child, ChildMut, Compound, MutableLeaves};
-pub use walk::{First, Step, Walk, Walker};
+pub use compound::{
+    Child, ChildMut, Compound, Fold, MutableLeaves, OwnedChild, Transaction,
+};
+pub use stats::Stats;
+pub use walk::{
+    Budgeted, Filtered, First, Last, Nth, NthLeaf, SampleWeighted, SkipUntil,
+    Step, TakeWhile, Walk, WalkError, Walker, DEFAULT_MAX_DEPTH,
+};
@@ -0,0 +1,92 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::compound::{Child, Compound};
+
+/// Statistics on the shape of a tree, for monitoring its health.
+///
+/// Depths are counted in the same way as [`Branch::depth`], with the leaves
+/// directly under the root at depth 1.
+///
+/// [`Branch::depth`]: crate::Branch::depth
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of nodes, including the root
+    pub nodes: u64,
+    /// The number of leaves
+    pub leaves: u64,
+    /// The number of empty slots
+    pub empty: u64,
+    /// The depth of the shallowest leaf, or 0 if there are no leaves
+    pub min_depth: usize,
+    /// The depth of the deepest leaf, or 0 if there are no leaves
+    pub max_depth: usize,
+    depth_sum: u64,
+}
+
+impl Stats {
+    /// Collects the statistics of the tree under the given root.
+    pub fn new<C, A>(root: &C) -> Self
+    where
+        C: Compound<A>,
+    {
+        let mut stats = Stats {
+            nodes: 1,
+            ..Stats::default()
+        };
+
+        let mut stack = vec![(root, 0)];
+        while let Some(top) = stack.last_mut() {
+            let (compound, index) = *top;
+            top.1 += 1;
+
+            match compound.child(index) {
+                Child::Leaf(_) => stats.add_leaf(stack.len()),
+                Child::Node(annotated) => {
+                    stats.nodes += 1;
+                    stack.push((annotated.child(), 0));
+                }
+                Child::Empty => stats.empty += 1,
+                Child::EndOfNode => {
+                    stack.pop();
+                }
+            }
+        }
+
+        stats
+    }
+
+    fn add_leaf(&mut self, depth: usize) {
+        if self.leaves == 0 || depth < self.min_depth {
+            self.min_depth = depth;
+        }
+        if depth > self.max_depth {
+            self.max_depth = depth;
+        }
+
+        self.leaves += 1;
+        self.depth_sum += depth as u64;
+    }
+
+    /// Returns the average depth of the leaves, or 0 if there are none
+    pub fn avg_depth(&self) -> f64 {
+        match self.leaves {
+            0 => 0.0,
+            leaves => self.depth_sum as f64 / leaves as f64,
+        }
+    }
+
+    /// Returns the fraction of child slots that are occupied by either a leaf
+    /// or a node, over all nodes of the tree
+    pub fn fill_factor(&self) -> f64 {
+        // every node but the root occupies a slot
+        let occupied = self.leaves + self.nodes - 1;
+        match occupied + self.empty {
+            0 => 0.0,
+            slots => occupied as f64 / slots as f64,
+        }
+    }
+}
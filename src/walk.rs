@@ -8,6 +8,8 @@ use crate::branch::Branch;
 use crate::branch_mut::BranchMut;
 use crate::compound::{Child, Compound, MutableLeaves};
 
+use alloc::vec::Vec;
+
 use core::marker::PhantomData;
 
 use ranno::Annotation;
@@ -28,21 +30,89 @@ pub enum Step {
     Abort,
 }
 
+/// The direction in which a tree is walked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    /// Returns the index a walk starts from when entering the given node
+    pub(crate) fn start<C, A>(self, compound: &C) -> usize
+    where
+        C: Compound<A>,
+    {
+        match self {
+            Direction::Forward => 0,
+            Direction::Backward => {
+                let mut len = 0;
+                while !matches!(compound.child(len), Child::EndOfNode) {
+                    len += 1;
+                }
+                len.saturating_sub(1)
+            }
+        }
+    }
+
+    /// Moves the index the given number of children in the walk direction
+    pub(crate) fn offset(self, index: usize, offset: usize) -> usize {
+        match self {
+            Direction::Forward => index + offset,
+            Direction::Backward => index - offset,
+        }
+    }
+
+    /// Returns the opposite direction
+    pub(crate) fn reversed(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
 /// The argument given to a [`Walker`] to traverse through nodes.
 pub struct Walk<'a, C, A> {
     index: usize,
+    skip: usize,
     compound: &'a C,
+    direction: Direction,
     _marker: PhantomData<A>,
 }
 
+// Manually implemented, since deriving would require `C` and `A` to be `Clone`
+impl<'a, C, A> Clone for Walk<'a, C, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, C, A> Copy for Walk<'a, C, A> {}
+
 impl<'a, C, A> Walk<'a, C, A> {
-    pub(crate) fn new(compound: &'a C, index: usize) -> Self {
+    pub(crate) fn new(
+        compound: &'a C,
+        index: usize,
+        direction: Direction,
+    ) -> Self {
         Walk {
             index,
+            skip: 0,
             compound,
+            direction,
             _marker: PhantomData,
         }
     }
+
+    /// Returns the same walk, with the given number of children hidden from
+    /// the start.
+    fn skip(&self, skip: usize) -> Self {
+        Walk {
+            skip: self.skip + skip,
+            ..*self
+        }
+    }
 }
 
 impl<'a, C, A> Walk<'a, C, A>
@@ -50,15 +120,93 @@ where
     C: Compound<A>,
 {
     /// Returns the child at specific index relative to the branch index
+    ///
+    /// When walking in reverse, children are counted backwards from the branch
+    /// index towards the first child of the node.
     pub fn child(&self, index: usize) -> Child<'a, C, A> {
-        self.compound.child(index + self.index)
+        let index = index + self.skip;
+        match self.direction {
+            Direction::Forward => self.compound.child(index + self.index),
+            Direction::Backward => match self.index.checked_sub(index) {
+                Some(index) => self.compound.child(index),
+                None => Child::EndOfNode,
+            },
+        }
     }
 }
 
 /// The trait used to construct a [`Branch`] or to iterate through a tree.
 pub trait Walker<C, A> {
     /// Walk the tree node, returning the appropriate [`Step`]
-    fn walk(&mut self, walk: Walk<C, A>) -> Step;
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step;
+}
+
+/// The default maximum depth of a [`Budgeted`] walk.
+///
+/// Walks that are not [`Budgeted`], such as [`Branch::walk`] and the branch
+/// iterators, are not limited in depth.
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// The error returned when a walk is stopped before completing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalkError {
+    /// The walk ran out of steps
+    BudgetExceeded,
+    /// The walk attempted to descend deeper than allowed
+    DepthLimitExceeded {
+        /// The child indices leading to the node that could not be entered
+        path: Vec<usize>,
+    },
+}
+
+/// Wraps a [`Walker`], bounding the number of steps a walk may take and how
+/// deep it may descend.
+///
+/// Every node the walker is asked about counts as one step, including the
+/// nodes visited while descending into or backing out of subtrees. Walks
+/// constructed with [`unbounded`] take any number of steps. The depth is
+/// bounded by [`DEFAULT_MAX_DEPTH`] unless set using [`with_max_depth`].
+///
+/// See [`Branch::walk_budgeted`] and [`BranchMut::walk_budgeted`].
+///
+/// [`unbounded`]: Budgeted::unbounded
+/// [`with_max_depth`]: Budgeted::with_max_depth
+#[derive(Debug)]
+pub struct Budgeted<W> {
+    walker: W,
+    steps: Option<u64>,
+    max_depth: usize,
+}
+
+impl<W> Budgeted<W> {
+    /// Wrap the given walker, allowing it to take at most `steps` steps.
+    pub fn new(walker: W, steps: u64) -> Self {
+        Budgeted {
+            walker,
+            steps: Some(steps),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Wrap the given walker, allowing it to take any number of steps, while
+    /// still bounding its depth.
+    pub fn unbounded(walker: W) -> Self {
+        Budgeted {
+            walker,
+            steps: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Set the maximum depth of the branch the walk may construct.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub(crate) fn parts_mut(&mut self) -> (&mut W, Option<&mut u64>, usize) {
+        (&mut self.walker, self.steps.as_mut(), self.max_depth)
+    }
 }
 
 /// Walker that visits all leaves
@@ -68,7 +216,7 @@ impl<C, A> Walker<C, A> for AllLeaves
 where
     C: Compound<A>,
 {
-    fn walk(&mut self, walk: Walk<C, A>) -> Step {
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
         for i in 0.. {
             match walk.child(i) {
                 Child::Leaf(_) => return Step::Found(i),
@@ -81,6 +229,268 @@ where
     }
 }
 
+/// Walker combinator pruning the subtrees whose annotation does not satisfy a
+/// predicate.
+///
+/// The wrapped walker is prevented from entering any node for which the
+/// predicate returns `false`, and is asked to walk the remaining children
+/// instead. Leaves carry no annotation, and are never pruned.
+pub struct Filtered<W, F> {
+    walker: W,
+    predicate: F,
+}
+
+impl<W, F> Filtered<W, F> {
+    /// Wrap the given walker, pruning subtrees using the given predicate.
+    pub fn new(walker: W, predicate: F) -> Self {
+        Filtered { walker, predicate }
+    }
+}
+
+impl<C, A, W, F> Walker<C, A> for Filtered<W, F>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    W: Walker<C, A>,
+    F: FnMut(&A) -> bool,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        let predicate = &mut self.predicate;
+        walk_pruned(&mut self.walker, walk, |anno| !predicate(anno))
+    }
+}
+
+/// Walker combinator ending the walk at the first subtree whose annotation
+/// does not satisfy a predicate.
+///
+/// Once the wrapped walker attempts to enter a node for which the predicate
+/// returns `false`, the walk is aborted, and stays aborted on any subsequent
+/// walk.
+pub struct TakeWhile<W, F> {
+    walker: W,
+    predicate: F,
+    done: bool,
+}
+
+impl<W, F> TakeWhile<W, F> {
+    /// Wrap the given walker, ending the walk using the given predicate.
+    pub fn new(walker: W, predicate: F) -> Self {
+        TakeWhile {
+            walker,
+            predicate,
+            done: false,
+        }
+    }
+}
+
+impl<C, A, W, F> Walker<C, A> for TakeWhile<W, F>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    W: Walker<C, A>,
+    F: FnMut(&A) -> bool,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        if self.done {
+            return Step::Abort;
+        }
+
+        // walk a copy, keeping the original to inspect the entered node
+        let step = self.walker.walk(walk);
+        if let Step::Into(i) = step {
+            if let Child::Node(node) = walk.child(i) {
+                if !(self.predicate)(&node.anno()) {
+                    self.done = true;
+                    return Step::Abort;
+                }
+            }
+        }
+        step
+    }
+}
+
+/// Walker combinator pruning subtrees until one whose annotation satisfies a
+/// predicate is found.
+///
+/// The wrapped walker is prevented from entering any node for which the
+/// predicate returns `false`, up until the first node for which it returns
+/// `true`. From then on, no more subtrees are pruned.
+pub struct SkipUntil<W, F> {
+    walker: W,
+    predicate: F,
+    found: bool,
+}
+
+impl<W, F> SkipUntil<W, F> {
+    /// Wrap the given walker, pruning subtrees using the given predicate.
+    pub fn new(walker: W, predicate: F) -> Self {
+        SkipUntil {
+            walker,
+            predicate,
+            found: false,
+        }
+    }
+}
+
+impl<C, A, W, F> Walker<C, A> for SkipUntil<W, F>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    W: Walker<C, A>,
+    F: FnMut(&A) -> bool,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        if self.found {
+            return self.walker.walk(walk);
+        }
+
+        let predicate = &mut self.predicate;
+        let found = &mut self.found;
+        walk_pruned(&mut self.walker, walk, |anno| {
+            *found = predicate(anno);
+            !*found
+        })
+    }
+}
+
+/// Walks the node with the given walker, asking it to walk the remaining
+/// children whenever it attempts to enter a node that should be pruned.
+fn walk_pruned<C, A, W, P>(
+    walker: &mut W,
+    walk: Walk<'_, C, A>,
+    mut prune: P,
+) -> Step
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    W: Walker<C, A>,
+    P: FnMut(&A) -> bool,
+{
+    let mut skipped = 0;
+    loop {
+        match walker.walk(walk.skip(skipped)) {
+            Step::Found(i) => return Step::Found(skipped + i),
+            Step::Into(i) => {
+                let index = skipped + i;
+                if let Child::Node(node) = walk.child(index) {
+                    if prune(&node.anno()) {
+                        skipped = index + 1;
+                        continue;
+                    }
+                }
+                return Step::Into(index);
+            }
+            step => return step,
+        }
+    }
+}
+
+/// Walker finding the nth leaf, counting from zero and visiting all leaves
+/// before it.
+///
+/// See [`Nth`] for constructing branches to the nth leaf directly.
+pub struct NthLeaf(pub u64);
+
+impl<C, A> Walker<C, A> for NthLeaf
+where
+    C: Compound<A>,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        for i in 0.. {
+            match walk.child(i) {
+                Child::Leaf(_) if self.0 == 0 => return Step::Found(i),
+                Child::Leaf(_) => self.0 -= 1,
+                Child::Node(_) => return Step::Into(i),
+                Child::Empty => (),
+                Child::EndOfNode => return Step::Advance,
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Walker picking a leaf according to its weight, for weighted random
+/// sampling.
+///
+/// Nodes are weighed by their annotation, and leaves by their value, using the
+/// given closures. The weight of a node must be the sum of the weights of the
+/// leaves under it. Given a `target` drawn uniformly at random from `0..total`,
+/// with `total` the weight of the whole tree, each leaf is found with a
+/// probability proportional to its weight. Targets past the total weight find
+/// no leaf.
+pub struct SampleWeighted<N, L> {
+    target: u64,
+    node_weight: N,
+    leaf_weight: L,
+}
+
+impl<N, L> SampleWeighted<N, L> {
+    /// Construct a walker finding the leaf at the given cumulative weight.
+    pub fn new(target: u64, node_weight: N, leaf_weight: L) -> Self {
+        SampleWeighted {
+            target,
+            node_weight,
+            leaf_weight,
+        }
+    }
+}
+
+impl<C, A, N, L> Walker<C, A> for SampleWeighted<N, L>
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+    N: FnMut(&A) -> u64,
+    L: FnMut(&C::Leaf) -> u64,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        for i in 0.. {
+            let (weight, step) = match walk.child(i) {
+                Child::Leaf(leaf) => ((self.leaf_weight)(leaf), Step::Found(i)),
+                Child::Node(node) => {
+                    ((self.node_weight)(&*node.anno()), Step::Into(i))
+                }
+                Child::Empty => continue,
+                Child::EndOfNode => return Step::Advance,
+            };
+            match self.target.checked_sub(weight) {
+                Some(target) => self.target = target,
+                None => return step,
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Walker that follows a path of child indices, finding the leaf at its end
+pub(crate) struct FollowPath<'p> {
+    path: &'p [usize],
+}
+
+impl<'p> FollowPath<'p> {
+    pub(crate) fn new(path: &'p [usize]) -> Self {
+        FollowPath { path }
+    }
+}
+
+impl<'p, C, A> Walker<C, A> for FollowPath<'p>
+where
+    C: Compound<A>,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        let (index, rest) = match self.path.split_first() {
+            Some(split) => split,
+            None => return Step::Abort,
+        };
+        self.path = rest;
+
+        match (walk.child(*index), rest.is_empty()) {
+            (Child::Leaf(_), true) => Step::Found(*index),
+            (Child::Node(_), false) => Step::Into(*index),
+            _ => Step::Abort,
+        }
+    }
+}
+
 /// Trait that provides a [`first`] and [`first_mut`] method to any
 /// [`Compound`].
 ///
@@ -88,10 +498,10 @@ where
 /// [`first_mut`]: First::first_mut
 pub trait First<A>: Sized + Compound<A> {
     /// Construct a [`Branch`] pointing to the first element, if not empty
-    fn first(&self) -> Option<Branch<Self, A>>;
+    fn first(&self) -> Option<Branch<'_, Self, A>>;
 
     /// Construct a [`BranchMut`] pointing to the first element, if not empty
-    fn first_mut(&mut self) -> Option<BranchMut<Self, A>>
+    fn first_mut(&mut self) -> Option<BranchMut<'_, Self, A>>
     where
         Self: MutableLeaves;
 }
@@ -101,14 +511,80 @@ where
     C: Compound<A>,
     A: Annotation<C>,
 {
-    fn first(&self) -> Option<Branch<Self, A>> {
+    fn first(&self) -> Option<Branch<'_, Self, A>> {
         Branch::walk(self, AllLeaves)
     }
 
-    fn first_mut(&mut self) -> Option<BranchMut<Self, A>>
+    fn first_mut(&mut self) -> Option<BranchMut<'_, Self, A>>
     where
         C: MutableLeaves,
     {
         BranchMut::walk(self, AllLeaves)
     }
 }
+
+/// Trait that provides a [`last`] and [`last_mut`] method to any
+/// [`Compound`].
+///
+/// [`last`]: Last::last
+/// [`last_mut`]: Last::last_mut
+pub trait Last<A>: Sized + Compound<A> {
+    /// Construct a [`Branch`] pointing to the last element, if not empty
+    fn last(&self) -> Option<Branch<'_, Self, A>>;
+
+    /// Construct a [`BranchMut`] pointing to the last element, if not empty
+    fn last_mut(&mut self) -> Option<BranchMut<'_, Self, A>>
+    where
+        Self: MutableLeaves;
+}
+
+impl<C, A> Last<A> for C
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+{
+    fn last(&self) -> Option<Branch<'_, Self, A>> {
+        Branch::walk_rev(self, AllLeaves)
+    }
+
+    fn last_mut(&mut self) -> Option<BranchMut<'_, Self, A>>
+    where
+        C: MutableLeaves,
+    {
+        BranchMut::walk_rev(self, AllLeaves)
+    }
+}
+
+/// Trait that provides a [`nth`] and [`nth_mut`] method to any [`Compound`].
+///
+/// Leaves are counted in walk order, and all the leaves preceding the nth are
+/// visited to find it.
+///
+/// [`nth`]: Nth::nth
+/// [`nth_mut`]: Nth::nth_mut
+pub trait Nth<A>: Sized + Compound<A> {
+    /// Construct a [`Branch`] pointing to the nth element, if any
+    fn nth(&self, n: u64) -> Option<Branch<'_, Self, A>>;
+
+    /// Construct a [`BranchMut`] pointing to the nth element, if any
+    fn nth_mut(&mut self, n: u64) -> Option<BranchMut<'_, Self, A>>
+    where
+        Self: MutableLeaves;
+}
+
+impl<C, A> Nth<A> for C
+where
+    C: Compound<A>,
+    A: Annotation<C>,
+{
+    fn nth(&self, n: u64) -> Option<Branch<'_, Self, A>> {
+        Branch::walk(self, NthLeaf(n))
+    }
+
+    fn nth_mut(&mut self, n: u64) -> Option<BranchMut<'_, Self, A>>
+    where
+        C: MutableLeaves,
+    {
+        BranchMut::walk(self, NthLeaf(n))
+    }
+}
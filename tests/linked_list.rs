@@ -4,7 +4,13 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use microkelvin::{Child, ChildMut, Compound, First, MutableLeaves};
+use microkelvin::{
+    verify_proof, Branch, BranchMut, Budgeted, Child, ChildMut, Compound,
+    Filtered, FindMinKey, First, Fold, HashAnnotation, Keyed, Last,
+    MerkleHasher, MinKey, MutableLeaves, Nth, NthLeaf, Observer, OwnedChild,
+    SampleWeighted, SkipUntil, Stats, Step, TakeWhile, Transaction, Walk,
+    WalkError, Walker,
+};
 use ranno::{Annotated, Annotation};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -31,15 +37,94 @@ impl<T> Annotation<LinkedList<T, Cardinality>> for Cardinality {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stake(u64);
+
+impl Annotation<LinkedList<u64, Stake>> for Stake {
+    fn from_child(t: &LinkedList<u64, Stake>) -> Self {
+        match t {
+            LinkedList::Empty => Stake(0),
+            LinkedList::Node { val, next } => Stake(next.anno().0 + val),
+        }
+    }
+}
+
+/// FNV-1a, widened to a 32 byte digest by hashing with different seeds. Stands
+/// in for a cryptographic hash function.
+struct Fnv;
+
+impl Fnv {
+    fn digest(bytes: &[u8]) -> [u8; 32] {
+        let mut digest = [0; 32];
+        for (seed, chunk) in digest.chunks_mut(8).enumerate() {
+            let hash =
+                bytes.iter().fold(0xcbf29ce484222325 ^ seed as u64, |h, b| {
+                    (h ^ *b as u64).wrapping_mul(0x100000001b3)
+                });
+            chunk.copy_from_slice(&hash.to_le_bytes());
+        }
+        digest
+    }
+}
+
+impl MerkleHasher<u64> for Fnv {
+    type Digest = [u8; 32];
+
+    fn leaf(leaf: &u64) -> Self::Digest {
+        Fnv::digest(&leaf.to_le_bytes())
+    }
+
+    fn node<I>(children: I) -> Self::Digest
+    where
+        I: Iterator<Item = Option<Self::Digest>>,
+    {
+        let mut bytes = vec![];
+        for child in children {
+            match child {
+                Some(digest) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&digest);
+                }
+                None => bytes.push(0),
+            }
+        }
+        Fnv::digest(&bytes)
+    }
+}
+
+type Hashed = HashAnnotation<Fnv, u64>;
+
+impl Annotation<LinkedList<u64, Hashed>> for Hashed {
+    fn from_child(t: &LinkedList<u64, Hashed>) -> Self {
+        HashAnnotation::compute(t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry(u64);
+
+impl Keyed<u64> for Entry {
+    fn key(&self) -> &u64 {
+        &self.0
+    }
+}
+
+impl<T: Keyed<u64>> Annotation<LinkedList<T, MinKey<u64>>> for MinKey<u64> {
+    fn from_child(t: &LinkedList<T, MinKey<u64>>) -> Self {
+        MinKey::compute(t)
+    }
+}
+
 impl<T> Annotation<LinkedList<T, ()>> for () {
     fn from_child(_: &LinkedList<T, ()>) -> Self {}
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum LinkedList<T, A>
 where
     A: Annotation<Self>,
 {
+    #[default]
     Empty,
     Node {
         val: T,
@@ -47,22 +132,13 @@ where
     },
 }
 
-impl<T, A> Default for LinkedList<T, A>
-where
-    A: Annotation<Self>,
-{
-    fn default() -> Self {
-        LinkedList::Empty
-    }
-}
-
 impl<T, A> Compound<A> for LinkedList<T, A>
 where
     A: Annotation<Self>,
 {
     type Leaf = T;
 
-    fn child(&self, index: usize) -> Child<Self, A> {
+    fn child(&self, index: usize) -> Child<'_, Self, A> {
         match (self, index) {
             (LinkedList::Node { val, .. }, 0) => Child::Leaf(val),
             (LinkedList::Node { next, .. }, 1) => Child::Node(next),
@@ -71,7 +147,7 @@ where
         }
     }
 
-    fn child_mut(&mut self, index: usize) -> ChildMut<Self, A> {
+    fn child_mut(&mut self, index: usize) -> ChildMut<'_, Self, A> {
         match (self, index) {
             (LinkedList::Node { val, .. }, 0) => ChildMut::Leaf(val),
             (LinkedList::Node { next, .. }, 1) => ChildMut::Node(next),
@@ -204,3 +280,622 @@ fn deref_mapped_mutable_branch() {
 
     assert_eq!(*mapped, 31);
 }
+
+#[test]
+fn observe_leaf_update() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let mut events = vec![];
+
+    let branch_mut = list.first_mut().expect("Some(branch_mut)");
+    let mut observed =
+        branch_mut.observe(|path: &[usize], old: &u64, new: &u64| {
+            events.push((path.to_vec(), *old, *new))
+        });
+
+    *observed += 100;
+    drop(observed);
+
+    let branch_mut = list.first_mut().expect("Some(branch_mut)");
+    let mut observed =
+        branch_mut.observe(|path: &[usize], old: &u64, new: &u64| {
+            events.push((path.to_vec(), *old, *new))
+        });
+
+    *observed += 0;
+    drop(observed);
+
+    assert_eq!(events, vec![(vec![0], 7, 107)]);
+    assert_eq!(list.pop(), Some(107));
+}
+
+/// Walker descending into every node without ever finding a leaf
+struct Descend;
+
+impl<C, A> Walker<C, A> for Descend
+where
+    C: Compound<A>,
+{
+    fn walk(&mut self, walk: Walk<'_, C, A>) -> Step {
+        for i in 0.. {
+            match walk.child(i) {
+                Child::Node(_) => return Step::Into(i),
+                Child::EndOfNode => return Step::Abort,
+                _ => (),
+            }
+        }
+        unreachable!()
+    }
+}
+
+#[test]
+fn walk_budgeted() {
+    let n: u64 = 32;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let exceeded = Branch::walk_budgeted(&list, Budgeted::new(Descend, 8));
+    assert_eq!(exceeded.err(), Some(WalkError::BudgetExceeded));
+
+    let aborted = Branch::walk_budgeted(&list, Budgeted::new(Descend, 64));
+    assert!(matches!(aborted, Ok(None)));
+}
+
+#[test]
+fn walk_depth_limit() {
+    let n: u64 = 32;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let walker = Budgeted::new(Descend, 64).with_max_depth(3);
+    let exceeded = Branch::walk_budgeted(&list, walker);
+
+    assert_eq!(
+        exceeded.err(),
+        Some(WalkError::DepthLimitExceeded {
+            path: vec![1, 1, 1]
+        })
+    );
+
+    let walker = Budgeted::unbounded(Descend).with_max_depth(3);
+    let exceeded = Branch::walk_budgeted(&list, walker);
+    assert!(matches!(
+        exceeded,
+        Err(WalkError::DepthLimitExceeded { .. })
+    ));
+
+    let walker = Budgeted::unbounded(Descend);
+    assert!(Branch::walk_budgeted(&list, walker).is_ok());
+}
+
+#[test]
+fn join() {
+    let n: u64 = 16;
+
+    let mut data = LinkedList::<_, ()>::new();
+    let mut index = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        data.push(i * 10);
+        index.push(n - 1 - i);
+    }
+
+    let branch = Branch::walk(&index, NthLeaf(3)).expect("Some(branch)");
+    let (branch, joined) = branch
+        .join(&data, |position| NthLeaf(*position))
+        .expect("Some(joined)");
+
+    assert_eq!(*branch, 3);
+    assert_eq!(*joined, (n - 1 - 3) * 10);
+}
+
+#[test]
+fn iterate_reverse() {
+    let n: u64 = 32;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    assert!(list.last().is_none());
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let branch = list.last().expect("Some(branch)");
+    let leaves: Vec<_> = branch.into_iter().copied().collect();
+    assert_eq!(leaves, (0..n).collect::<Vec<_>>());
+
+    let branch_mut = list.last_mut().expect("Some(branch_mut)");
+    for leaf in branch_mut {
+        *leaf *= 2;
+    }
+
+    let first = list.first().expect("Some(branch)");
+    assert_eq!(*first, (n - 1) * 2);
+}
+
+#[test]
+fn annotation_at() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let anno = list.annotation_at(&[1]).map(|a| *a);
+    assert_eq!(anno, Some(Cardinality(7)));
+
+    let anno = list.annotation_at(&[1, 1, 1]).map(|a| *a);
+    assert_eq!(anno, Some(Cardinality(5)));
+
+    assert!(list.annotation_at(&[]).is_none());
+    assert!(list.annotation_at(&[0]).is_none());
+    assert!(list.annotation_at(&[1, 0]).is_none());
+}
+
+#[test]
+fn nth() {
+    let n: u64 = 32;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    for i in 0..n {
+        let branch = list.nth(i).expect("Some(branch)");
+        assert_eq!(*branch, n - 1 - i);
+    }
+    assert!(list.nth(n).is_none());
+
+    let mut branch_mut = list.nth_mut(4).expect("Some(branch_mut)");
+    *branch_mut = 100;
+
+    let branch = list.nth(4).expect("Some(branch)");
+    assert_eq!(*branch, 100);
+}
+
+#[test]
+fn replace_child() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let replaced = list.replace_child(0, OwnedChild::Leaf(100));
+    assert!(matches!(replaced, Ok(OwnedChild::Leaf(7))));
+
+    let mut other = LinkedList::new();
+    other.push(42);
+
+    let node = OwnedChild::Node(Annotated::new(Box::new(other)));
+    let replaced = list.replace_child(1, node);
+    assert!(matches!(replaced, Ok(OwnedChild::Node(_))));
+
+    let mismatched = list.replace_child(
+        0,
+        OwnedChild::Node(Annotated::new(Box::new(LinkedList::new()))),
+    );
+    assert!(matches!(mismatched, Err(OwnedChild::Node(_))));
+
+    assert_eq!(list.pop(), Some(100));
+    assert_eq!(list.pop(), Some(42));
+    assert_eq!(list.pop(), None);
+}
+
+#[test]
+fn path_replay() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let branch = list.nth(3).expect("Some(branch)");
+    let path: Vec<_> = branch.path().collect();
+    assert_eq!(path, vec![1, 1, 1, 0]);
+
+    let replayed = Branch::from_path(&list, &path).expect("Some(branch)");
+    assert_eq!(*replayed, n - 1 - 3);
+
+    assert!(Branch::from_path(&list, &[1, 1]).is_none());
+    assert!(Branch::from_path(&list, &[0, 1]).is_none());
+    assert!(Branch::from_path(&list, &[]).is_none());
+
+    let mut branch_mut =
+        BranchMut::from_path(&mut list, &path).expect("Some(branch_mut)");
+    *branch_mut = 100;
+    assert_eq!(branch_mut.path().collect::<Vec<_>>(), path);
+    drop(branch_mut);
+
+    assert_eq!(*list.nth(3).expect("Some(branch)"), 100);
+}
+
+#[test]
+fn pruning_combinators() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    // subtrees with 4 elements or less are pruned
+    let large = |c: &Cardinality| c.0 > 4;
+
+    let walker = Filtered::new(NthLeaf(3), large);
+    let branch = Branch::walk(&list, walker).expect("Some(branch)");
+    assert_eq!(*branch, 4);
+
+    let walker = Filtered::new(NthLeaf(4), large);
+    assert!(Branch::walk(&list, walker).is_none());
+
+    let walker = TakeWhile::new(NthLeaf(2), large);
+    let branch = Branch::walk(&list, walker).expect("Some(branch)");
+    assert_eq!(*branch, 5);
+
+    let walker = TakeWhile::new(NthLeaf(6), large);
+    assert!(Branch::walk(&list, walker).is_none());
+
+    // the first leaf is never pruned, all subtrees are
+    let walker = SkipUntil::new(NthLeaf(0), |c: &Cardinality| c.0 > 8);
+    let branch = Branch::walk(&list, walker).expect("Some(branch)");
+    assert_eq!(*branch, 7);
+
+    let walker = SkipUntil::new(NthLeaf(1), |c: &Cardinality| c.0 > 8);
+    assert!(Branch::walk(&list, walker).is_none());
+
+    let walker = SkipUntil::new(NthLeaf(6), |c: &Cardinality| c.0 < 8);
+    let branch = Branch::walk(&list, walker).expect("Some(branch)");
+    assert_eq!(*branch, 1);
+}
+
+#[test]
+fn fold_annotations() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    // count the elements, reading at most three leaves
+    let (count, leaves) = list.fold_annotations(
+        (0, 0),
+        |(count, _), c| {
+            if c.0 <= 5 {
+                *count += c.0;
+                Fold::Continue
+            } else {
+                Fold::Descend
+            }
+        },
+        |(count, leaves), _| {
+            *count += 1;
+            *leaves += 1;
+            Fold::Continue
+        },
+    );
+    assert_eq!(count, 8);
+    assert_eq!(leaves, 3);
+
+    // sum the leaves, stopping at the first below 5
+    let sum = list.fold_annotations(
+        0,
+        |_, _| Fold::Descend,
+        |sum, leaf| {
+            if *leaf < 5 {
+                return Fold::Stop;
+            }
+            *sum += leaf;
+            Fold::Continue
+        },
+    );
+    assert_eq!(sum, 7 + 6 + 5);
+}
+
+#[test]
+fn transaction() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let failed: Result<(), ()> = list.transaction(|tx| {
+        *tx.first_mut().expect("Some(branch_mut)") = 100;
+        tx.pop();
+        Err(())
+    });
+    assert!(failed.is_err());
+    assert_eq!(*list.first().expect("Some(branch)"), 7);
+
+    let popped: Result<_, ()> = list.transaction(|tx| {
+        let popped = tx.pop();
+        *tx.first_mut().expect("Some(branch_mut)") = 100;
+        Ok(popped)
+    });
+    assert_eq!(popped, Ok(Some(7)));
+    assert_eq!(list.pop(), Some(100));
+    assert_eq!(list.pop(), Some(5));
+}
+
+#[test]
+fn stats() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    let stats = Stats::new(&list);
+    assert_eq!(stats.nodes, 1);
+    assert_eq!(stats.leaves, 0);
+    assert_eq!(stats.fill_factor(), 0.0);
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let stats = Stats::new(&list);
+    assert_eq!(stats.nodes, n + 1);
+    assert_eq!(stats.leaves, n);
+    assert_eq!(stats.empty, 0);
+    assert_eq!(stats.min_depth, 1);
+    assert_eq!(stats.max_depth, n as usize);
+    assert_eq!(stats.avg_depth(), 4.5);
+    assert_eq!(stats.fill_factor(), 1.0);
+}
+
+#[test]
+fn branch_annotations() {
+    let n: u64 = 4;
+
+    let mut list = LinkedList::<_, Cardinality>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let branch = list.last().expect("Some(branch)");
+    let annos: Vec<_> = branch.annotations().map(|a| *a).collect();
+    assert_eq!(annos, vec![Cardinality(3), Cardinality(2), Cardinality(1)]);
+    assert!(branch.levels()[0].annotation().is_none());
+
+    let branch_mut = list.nth_mut(2).expect("Some(branch_mut)");
+    let annos = branch_mut.into_annotations();
+    assert_eq!(annos, vec![Cardinality(3), Cardinality(2)]);
+}
+
+#[test]
+fn branch_mut_annotations_after_update() {
+    let mut list = LinkedList::<_, Stake>::new();
+
+    for stake in 1..=5 {
+        list.push(stake)
+    }
+
+    let mut branch_mut = list.nth_mut(3).expect("Some(branch_mut)");
+    *branch_mut += 100;
+    let annos = branch_mut.into_annotations();
+    assert_eq!(annos, vec![Stake(110), Stake(106), Stake(103)]);
+
+    assert_eq!(list.annotation_at(&[1]).map(|a| *a), Some(Stake(110)));
+    assert_eq!(list.annotation_at(&[1, 1]).map(|a| *a), Some(Stake(106)));
+
+    let branch = list.nth(3).expect("Some(branch)");
+    let annos: Vec<_> = branch.annotations().map(|a| *a).collect();
+    assert_eq!(annos, vec![Stake(110), Stake(106), Stake(103)]);
+
+    *list.nth_mut(3).expect("Some(branch_mut)") -= 100;
+    assert_eq!(list.annotation_at(&[1]).map(|a| *a), Some(Stake(10)));
+    assert_eq!(list.annotation_at(&[1, 1]).map(|a| *a), Some(Stake(6)));
+}
+
+#[test]
+fn reverse_branch() {
+    let n: u64 = 8;
+
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..n {
+        list.push(i)
+    }
+
+    let branch = list.nth(4).expect("Some(branch)");
+    let leaves: Vec<_> = branch.rev().into_iter().copied().collect();
+    assert_eq!(leaves, vec![3, 4, 5, 6, 7]);
+
+    let branch = list.last().expect("Some(branch)");
+    let leaves: Vec<_> = branch.rev().into_iter().copied().collect();
+    assert_eq!(leaves, vec![0]);
+
+    let branch_mut = list.nth_mut(2).expect("Some(branch_mut)");
+    for leaf in branch_mut.rev() {
+        *leaf *= 2;
+    }
+
+    let leaves: Vec<_> = list.first().unwrap().into_iter().copied().collect();
+    assert_eq!(leaves, vec![14, 12, 10, 4, 3, 2, 1, 0]);
+}
+
+#[test]
+fn sample_weighted() {
+    let mut list = LinkedList::<_, Stake>::new();
+
+    for stake in [3, 0, 5, 2] {
+        list.push(stake)
+    }
+
+    let total = Stake::from_child(&list).0;
+    assert_eq!(total, 10);
+
+    let sample = |target| {
+        let walker = SampleWeighted::new(target, |s: &Stake| s.0, |l: &u64| *l);
+        Branch::walk(&list, walker).map(|branch| *branch)
+    };
+
+    let sampled: Vec<_> = (0..total).map(|t| sample(t).unwrap()).collect();
+    assert_eq!(sampled, vec![2, 2, 5, 5, 5, 5, 5, 3, 3, 3]);
+    assert_eq!(sample(total), None);
+}
+
+#[test]
+fn hash_annotation() {
+    let mut list = LinkedList::<_, Hashed>::new();
+    list.push(7);
+
+    let empty = Fnv::node(core::iter::empty());
+    let root = Fnv::node([Some(Fnv::leaf(&7)), Some(empty)].into_iter());
+    assert_eq!(Hashed::compute(&list).digest(), root);
+
+    let mut list = LinkedList::<_, Hashed>::new();
+    let mut other = LinkedList::<_, Hashed>::new();
+
+    for i in 0..8u64 {
+        list.push(i);
+        other.push(i);
+    }
+
+    let root = Hashed::compute(&list);
+    assert_eq!(root, Hashed::compute(&other));
+
+    *other.nth_mut(5).expect("Some(branch_mut)") += 1;
+    assert_ne!(root, Hashed::compute(&other));
+
+    *other.nth_mut(5).expect("Some(branch_mut)") -= 1;
+    assert_eq!(root, Hashed::compute(&other));
+}
+
+#[test]
+fn find_min_key() {
+    let mut list = LinkedList::<_, MinKey<u64>>::new();
+
+    assert!(Branch::walk(&list, FindMinKey).is_none());
+
+    for key in [5, 3, 8, 1, 9] {
+        list.push(Entry(key))
+    }
+
+    assert_eq!(MinKey::compute(&list), MinKey::Minimum(1));
+
+    let branch = Branch::walk(&list, FindMinKey).expect("Some(branch)");
+    assert_eq!(*branch, Entry(1));
+
+    let mut branch_mut =
+        BranchMut::walk(&mut list, FindMinKey).expect("Some(branch_mut)");
+    *branch_mut = Entry(10);
+    drop(branch_mut);
+
+    let branch = Branch::walk(&list, FindMinKey).expect("Some(branch)");
+    assert_eq!(*branch, Entry(3));
+}
+
+#[test]
+fn leaf_accessors() {
+    let mut list = LinkedList::<_, ()>::new();
+
+    for i in 0..4u64 {
+        list.push((i, i * 10))
+    }
+
+    let branch = list.nth(1).expect("Some(branch)");
+    assert_eq!(*branch.leaf(), (2, 20));
+
+    let mapped = branch.map_leaf(|(_, v)| v);
+    assert_eq!(*mapped.leaf(), 20);
+}
+
+#[test]
+fn merkle_proof() {
+    let mut list = LinkedList::<_, Hashed>::new();
+
+    for i in 0..8u64 {
+        list.push(i)
+    }
+
+    let root = Hashed::compute(&list).digest();
+
+    let branch = list.nth(5).expect("Some(branch)");
+    let proof = branch.proof();
+    assert_eq!(proof.levels.len(), 6);
+
+    assert!(verify_proof::<Fnv, _>(root, &proof, &2));
+    assert!(!verify_proof::<Fnv, _>(root, &proof, &3));
+
+    let mut tampered = proof.clone();
+    tampered.levels[0].index = 1;
+    assert!(!verify_proof::<Fnv, _>(root, &tampered, &2));
+
+    *list.nth_mut(0).expect("Some(branch_mut)") += 1;
+    let root = Hashed::compute(&list).digest();
+    assert!(!verify_proof::<Fnv, _>(root, &proof, &2));
+}
+
+#[derive(Default)]
+struct AnnotationLog {
+    leaves: Vec<(u64, u64)>,
+    annotations: Vec<(Vec<usize>, Vec<Stake>)>,
+}
+
+impl Observer<u64, Stake> for &mut AnnotationLog {
+    fn leaf_updated(&mut self, _: &[usize], old: &u64, new: &u64) {
+        self.leaves.push((*old, *new))
+    }
+
+    fn annotations_updated(&mut self, path: &[usize], annotations: &[Stake]) {
+        self.annotations.push((path.to_vec(), annotations.to_vec()))
+    }
+}
+
+#[test]
+fn observe_annotation_update() {
+    let mut list = LinkedList::<_, Stake>::new();
+
+    for stake in 1..=5 {
+        list.push(stake)
+    }
+
+    let mut log = AnnotationLog::default();
+
+    let branch_mut = list.nth_mut(3).expect("Some(branch_mut)");
+    let mut observed = branch_mut.observe(&mut log);
+    *observed += 100;
+    drop(observed);
+
+    let branch_mut = list.nth_mut(1).expect("Some(branch_mut)");
+    let mut observed = branch_mut.observe(&mut log);
+    *observed += 0;
+    drop(observed);
+
+    assert_eq!(log.leaves, vec![(2, 102)]);
+    assert_eq!(
+        log.annotations,
+        vec![(vec![1, 1, 1, 0], vec![Stake(110), Stake(106), Stake(103)])]
+    );
+    assert_eq!(list.annotation_at(&[1]).map(|a| *a), Some(Stake(110)));
+}
@@ -0,0 +1,157 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use microkelvin::{
+    Child, ChildMut, Compound, First, MutableLeaves, OwnedChild, Stats,
+};
+use ranno::{Annotated, Annotation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cardinality(usize);
+
+impl<T> Annotation<SlottedTree<T>> for Cardinality {
+    fn from_child(tree: &SlottedTree<T>) -> Self {
+        let c = tree
+            .slots
+            .iter()
+            .map(|slot| match slot {
+                Slot::Leaf(_) => 1,
+                Slot::Node(node) => node.anno().0,
+                Slot::Empty => 0,
+            })
+            .sum();
+        Cardinality(c)
+    }
+}
+
+/// A tree with a fixed number of slots per node, some of which may be empty
+#[derive(Debug, Default)]
+pub struct SlottedTree<T> {
+    slots: Vec<Slot<T>>,
+}
+
+#[derive(Debug)]
+enum Slot<T> {
+    Leaf(T),
+    Node(Annotated<Box<SlottedTree<T>>, Cardinality>),
+    Empty,
+}
+
+impl<T> Compound<Cardinality> for SlottedTree<T> {
+    type Leaf = T;
+
+    fn child(&self, index: usize) -> Child<'_, Self, Cardinality> {
+        match self.slots.get(index) {
+            Some(Slot::Leaf(leaf)) => Child::Leaf(leaf),
+            Some(Slot::Node(node)) => Child::Node(node),
+            Some(Slot::Empty) => Child::Empty,
+            None => Child::EndOfNode,
+        }
+    }
+
+    fn child_mut(&mut self, index: usize) -> ChildMut<'_, Self, Cardinality> {
+        match self.slots.get_mut(index) {
+            Some(Slot::Leaf(leaf)) => ChildMut::Leaf(leaf),
+            Some(Slot::Node(node)) => ChildMut::Node(node),
+            Some(Slot::Empty) => ChildMut::Empty,
+            None => ChildMut::EndOfNode,
+        }
+    }
+
+    fn take_child(
+        &mut self,
+        index: usize,
+    ) -> Option<OwnedChild<Self, Cardinality>> {
+        let slot = self.slots.get_mut(index)?;
+        match core::mem::replace(slot, Slot::Empty) {
+            Slot::Leaf(leaf) => Some(OwnedChild::Leaf(leaf)),
+            Slot::Node(node) => Some(OwnedChild::Node(node)),
+            Slot::Empty => None,
+        }
+    }
+}
+
+impl<T> MutableLeaves for SlottedTree<T> {}
+
+impl<T> SlottedTree<T> {
+    /// Builds a tree of the given height, with `width` slots per node
+    fn full(height: usize, width: usize, next: &mut impl FnMut() -> T) -> Self {
+        let slots = (0..width)
+            .map(|_| match height {
+                0 => Slot::Leaf(next()),
+                _ => Slot::Node(Annotated::new(Box::new(Self::full(
+                    height - 1,
+                    width,
+                    next,
+                )))),
+            })
+            .collect();
+        SlottedTree { slots }
+    }
+
+    /// Removes all empty slots, and the nodes left without children
+    fn compact(&mut self) {
+        self.slots.retain(|slot| match slot {
+            Slot::Empty => false,
+            Slot::Node(node) => !node.child().slots.is_empty(),
+            Slot::Leaf(_) => true,
+        })
+    }
+}
+
+fn leaves<T: Copy>(tree: &SlottedTree<T>) -> Vec<T> {
+    match tree.first() {
+        Some(branch) => branch.into_iter().copied().collect(),
+        None => vec![],
+    }
+}
+
+#[test]
+fn take_child() {
+    let mut i = 0;
+    let mut tree = SlottedTree::full(1, 2, &mut || {
+        i += 1;
+        i
+    });
+
+    let node = tree.take_child(0);
+    assert!(matches!(node, Some(OwnedChild::Node(_))));
+    assert!(matches!(tree.child(0), Child::Empty));
+    assert!(tree.take_child(0).is_none());
+    assert_eq!(Cardinality::from_child(&tree), Cardinality(2));
+
+    let leaf = match tree.child_mut(1) {
+        ChildMut::Node(node) => node.child_mut().take_child(0),
+        _ => panic!("Expected a node"),
+    };
+    assert!(matches!(leaf, Some(OwnedChild::Leaf(3))));
+    assert_eq!(Cardinality::from_child(&tree), Cardinality(1));
+}
+
+#[test]
+fn take_leaf() {
+    let mut i = 0;
+    let mut tree = SlottedTree::full(1, 2, &mut || {
+        i += 1;
+        i
+    });
+
+    let branch_mut = tree.first_mut().expect("Some(branch_mut)");
+    let leaf = branch_mut.take_leaf(|_| ());
+    assert_eq!(leaf, Some(1));
+
+    assert_eq!(leaves(&tree), vec![2, 3, 4]);
+    assert_eq!(Stats::new(&tree).empty, 1);
+
+    let branch_mut = tree.first_mut().expect("Some(branch_mut)");
+    let leaf = branch_mut.take_leaf(SlottedTree::compact);
+    assert_eq!(leaf, Some(2));
+
+    assert_eq!(leaves(&tree), vec![3, 4]);
+    assert_eq!(Stats::new(&tree).empty, 0);
+    assert_eq!(Stats::new(&tree).nodes, 2);
+    assert_eq!(Cardinality::from_child(&tree), Cardinality(2));
+}